@@ -6,18 +6,43 @@
 use std::str::FromStr;
 
 use seed::{prelude::*, *};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
 
 use base::{Header, Footer};
 
 use beer_song::sing;
 
+mod range;
+use range::Range;
+
 // ------ ------
 //     Init
 // ------ ------
 
 // `init` describes what should happen when your app started.
-fn init(_: Url, _: &mut impl Orders<Msg>) -> Model {
-    Model::default()
+fn init(_: Url, orders: &mut impl Orders<Msg>) -> Model {
+    let mut model = Model::default();
+
+    if let Some(mql) = prefers_dark_media_query() {
+        model.system_prefers_dark = mql.matches();
+
+        let msg_sender = orders.msg_sender();
+        let on_change = Closure::wrap(Box::new(move |event: web_sys::MediaQueryListEvent| {
+            msg_sender(Some(Msg::SystemThemeChanged(event.matches())));
+        }) as Box<dyn FnMut(web_sys::MediaQueryListEvent)>);
+        mql.set_onchange(Some(on_change.as_ref().unchecked_ref()));
+        on_change.forget();
+    }
+
+    model
+}
+
+/// Looks up the `(prefers-color-scheme: dark)` media query, if the browser supports it.
+fn prefers_dark_media_query() -> Option<web_sys::MediaQueryList> {
+    web_sys::window()?
+        .match_media("(prefers-color-scheme: dark)")
+        .ok()?
 }
 
 // ------ ------
@@ -25,67 +50,124 @@ fn init(_: Url, _: &mut impl Orders<Msg>) -> Model {
 // ------ ------
 
 // `Model` describes our app state.
-// buffers are used to hold the string when it isn't (yet) a valid number
-// otherwise the logic made it sometimes impossible (or stupidly awkward) to change values
-// buffers are rendered on the page, start and end are actually used in the calculation
-// it might make sense to group them under a custom struct so we can codify their linkage
+// `range` holds the raw text alongside the parsed, validated `(start, end)` pair
+// the buffer is rendered on the page, range.parsed is what's actually used in the calculation
 #[derive(Default)]
 struct Model {
-    start: TextBuffer<u32>,
-    end: TextBuffer<u32>,
+    range: TextBuffer<Range>,
+    playback: Playback,
+    theme: Theme,
+    system_prefers_dark: bool,
+}
+
+/// The user's theme preference. `Auto` follows the OS's `prefers-color-scheme`,
+/// tracked live in `Model::system_prefers_dark`.
+#[derive(Default, Clone, Copy, PartialEq)]
+enum Theme {
+    Light,
+    Dark,
+    #[default]
+    Auto,
 }
 
-/// Stores a raw text value and the parsed result
+impl Theme {
+    /// Resolves `Auto` against the current system preference to decide which CSS class applies.
+    fn resolved_class(self, system_prefers_dark: bool) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::Auto => if system_prefers_dark { "dark" } else { "light" },
+        }
+    }
+}
+
+/// A self-rendering validated input: stores a raw text value alongside the last-parsed result,
+/// and tracks whether that raw value currently fails validation so `view` can style it as an
+/// error rather than silently reverting to the last good value.
 #[derive(Default)]
 struct TextBuffer<T: FromStr> {
     pub raw: String,
     pub parsed: T,
+    pub error: bool,
 }
 
-/// parsing the raw text value may depend on external information
-/// this additional validation logic is parsed in as a closure
-/// which will need to capture from its calling environment if needed
-impl<T: FromStr + std::cmp::PartialEq + std::fmt::Display> TextBuffer<T> {
-    /// update the raw value, this method could easily have been named set_raw
-    pub fn update(&mut self, raw: String) {
-        self.raw = raw;
+/// Drives the karaoke-style "sing-along" playback of the current verse range.
+/// `current` is an index into `sing(start, end).lines()`, advanced on every `Tick`.
+/// `ticker` holds the handle to the running `streams::interval` subscription, if any is — it's
+/// dropped to cancel the interval, rather than merely ignoring `Tick` while `playing` is `false`.
+struct Playback {
+    playing: bool,
+    current: usize,
+    tempo_ms: u32,
+    read_aloud: bool,
+    ticker: Option<StreamHandle>,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            playing: false,
+            current: 0,
+            tempo_ms: 1500,
+            read_aloud: false,
+            ticker: None,
+        }
     }
+}
 
+/// parsing the raw text value may depend on external information
+/// this additional validation logic is passed in as a closure
+/// which will need to capture from its calling environment if needed
+impl<T: FromStr + std::fmt::Display> TextBuffer<T> {
     /// overwrite both the parsed and raw values by providing a ready parsed value
-    /// which is converted to a string
+    /// which is converted to a string, clearing any previous error state
     pub fn overwrite(&mut self, parsed: T) {
         self.raw = parsed.to_string();
         self.parsed = parsed;
+        self.error = false;
     }
 
-    /// parse the current raw value and store it
-    pub fn parse(&mut self) {
-        if let Ok(parsed) = self.raw.parse() {
-            self.parsed = parsed;
-        }
+    /// set the raw value to a freshly typed string and re-validate it
+    /// `on_update` receives `Some(parsed)` when the raw text parses, `None` otherwise, and should
+    /// return `Some(valid)` to commit a value or `None` to mark the buffer as errored
+    pub fn update_raw<F>(&mut self, raw: String, on_update: F)
+    where
+        F: Fn(Option<T>) -> Option<T>,
+    {
+        self.raw = raw;
+        self.revalidate(on_update);
     }
 
-    /// parse the current raw value and then store the result of it going through a given
-    /// validation function the provided closure takes a reference to the (successfully) parsed
-    /// value and should return Some(T) to store T or None to make no changes
-    pub fn validate<F>(&mut self, validate: F)
+    /// re-run validation against the current raw value without changing it
+    /// `update_raw` calls this after storing the freshly typed string
+    pub fn revalidate<F>(&mut self, on_update: F)
     where
-        F: Fn(&T) -> Option<T>
+        F: Fn(Option<T>) -> Option<T>,
     {
-        if let Ok(parsed) = self.raw.parse() {
-            if let Some(valid) = validate(&parsed) {
+        let candidate = self.raw.parse().ok();
+        match on_update(candidate) {
+            Some(valid) => {
                 self.parsed = valid;
-            }
+                self.error = false;
+            },
+            None => {
+                self.error = true;
+            },
         }
     }
 
-    /// returns true if the parsed value and raw value currently match
-    pub fn is_valid(&self) -> bool {
-        if let Ok(parsed) = self.raw.parse() {
-            self.parsed == parsed
-        } else {
-            false
-        }
+    /// renders the `<input>` for this buffer, wiring `on_input` up to `Ev::Input` and applying
+    /// an `is-error` class while the raw value fails validation
+    pub fn view<Ms: 'static>(&self, label: &str, on_input: impl FnOnce(String) -> Ms + Clone + 'static) -> Node<Ms> {
+        label![
+            C!["text-buffer"],
+            label,
+            input![
+                IF!(self.error => C!["is-error"]),
+                attrs!{ At::Type => "text", At::Value => self.raw, At::Placeholder => "99-0, 99..0, or 99 to 0" },
+                input_ev(Ev::Input, on_input)
+            ]
+        ]
     }
 }
 
@@ -95,56 +177,165 @@ impl<T: FromStr + std::cmp::PartialEq + std::fmt::Display> TextBuffer<T> {
 
 // `Msg` describes the different events you can modify state with.
 enum Msg {
-    UpdateStart(String),
-    UpdateEnd(String),
+    UpdateRange(String),
     FullSong,
     NextVerse,
+    Play,
+    Pause,
+    Stop,
+    Tick,
+    SetTempo(String),
+    ToggleReadAloud,
+    ExportLrc,
+    SetTheme(Theme),
+    SystemThemeChanged(bool),
 }
 
 // `update` describes how to handle each `Msg`.
-fn update(msg: Msg, model: &mut Model, _: &mut impl Orders<Msg>) {
+fn update(msg: Msg, model: &mut Model, orders: &mut impl Orders<Msg>) {
     match msg {
-        Msg::UpdateStart(raw) => {
-            // update the raw value
-            model.start.update(raw);
-            // check both start and end if their raw values have become valid (their validity is inter-dependent)
-            // note the order is significant here:
-            //   * update start first because it has just changed, by checking against the existing end value
-            //   * then with a new start value we should check if start is now valid
-            let prev_end = model.end.parsed;
-            model.start.validate(|start| if start >= &prev_end { Some(std::cmp::min(*start, 99)) } else { None });
-            let new_start = model.start.parsed;
-            model.end.validate(|end| if end <= &new_start { Some(std::cmp::min(*end, 99)) } else { None });
-        },
-        Msg::UpdateEnd(raw) => {
-            // update the raw value
-            model.end.update(raw);
-
-            // check both start and end if their raw values have become valid (their validity is inter-dependent)
-            // note the order is significant here:
-            //   * update end first because it has just changed, by checking against the existing start value
-            //   * then with a new end value we should check if start is now valid
-            let prev_start = model.start.parsed;
-            model.end.validate(|end| if end <= &prev_start { Some(std::cmp::min(*end, 99)) } else { None });
-            let new_end = model.end.parsed;
-            model.start.validate(|start| if start >= &new_end { Some(std::cmp::min(*start, 99)) } else { None });
+        Msg::UpdateRange(raw) => {
+            // `Range::from_str` already does the clamping and ordering validation, so there's
+            // nothing further for the buffer's on-commit closure to check
+            model.range.update_raw(raw, |candidate| candidate);
         },
         Msg::FullSong => {
-            model.start.overwrite(99);
-            model.end.overwrite(0);
+            model.range.overwrite(Range { start: 99, end: 0 });
         },
         Msg::NextVerse => {
-            match model.end.parsed {
-                0 => {
-                    model.start.overwrite(99);
-                    model.end.overwrite(99);
-                },
-                x => {
-                    model.end.overwrite(x - 1);
-                }
+            let start = model.range.parsed.start;
+            match model.range.parsed.end {
+                0 => model.range.overwrite(Range { start: 99, end: 99 }),
+                x => model.range.overwrite(Range { start, end: x - 1 }),
             };
         },
+        Msg::Play => {
+            if !model.playback.playing {
+                model.playback.playing = true;
+                if model.playback.read_aloud {
+                    speak_current_verse(model);
+                }
+                model.playback.ticker = Some(start_ticker(orders, model.playback.tempo_ms));
+            }
+        },
+        Msg::Pause => {
+            model.playback.playing = false;
+            model.playback.ticker = None;
+        },
+        Msg::Stop => {
+            model.playback.playing = false;
+            model.playback.ticker = None;
+            model.playback.current = 0;
+        },
+        Msg::Tick => {
+            if model.playback.playing {
+                let verse_count = sing(model.range.parsed.start, model.range.parsed.end).lines().count();
+                if model.playback.current + 1 < verse_count {
+                    model.playback.current += 1;
+                    if model.playback.read_aloud {
+                        speak_current_verse(model);
+                    }
+                } else {
+                    model.playback.playing = false;
+                    model.playback.ticker = None;
+                }
+            }
+        },
+        Msg::SetTempo(raw) => {
+            if let Ok(tempo_ms) = raw.parse() {
+                model.playback.tempo_ms = tempo_ms;
+                // restart the interval at the new tempo so the slider takes effect immediately,
+                // instead of only after the next Play/Stop cycle
+                if model.playback.playing {
+                    model.playback.ticker = Some(start_ticker(orders, tempo_ms));
+                }
+            }
+        },
+        Msg::ToggleReadAloud => {
+            model.playback.read_aloud = !model.playback.read_aloud;
+        },
+        Msg::ExportLrc => {
+            if let Some(lrc) = render_lrc(model.range.parsed.start, model.range.parsed.end, model.playback.tempo_ms) {
+                download_text_file("beer-song.lrc", &lrc);
+            }
+        },
+        Msg::SetTheme(theme) => {
+            model.theme = theme;
+        },
+        Msg::SystemThemeChanged(prefers_dark) => {
+            model.system_prefers_dark = prefers_dark;
+        },
+    }
+}
+
+/// Renders the verse range as a karaoke `.lrc` lyric file, one timestamped line per verse.
+/// Each verse is allotted `tempo_ms` before the next timestamp. Returns `None` if the range
+/// produces no lines, since an empty `.lrc` file isn't worth downloading.
+fn render_lrc(start: u32, end: u32, tempo_ms: u32) -> Option<String> {
+    let mut lines = sing(start, end).lines().peekable();
+    lines.peek()?;
 
+    let mut lrc = String::new();
+    let mut elapsed_ms: u32 = 0;
+    for verse in lines {
+        let minutes = elapsed_ms / 60_000;
+        let seconds = (elapsed_ms / 1_000) % 60;
+        let centiseconds = (elapsed_ms % 1_000) / 10;
+        lrc.push_str(&format!("[{minutes:02}:{seconds:02}.{centiseconds:02}]{verse}\n"));
+        elapsed_ms += tempo_ms;
+    }
+    Some(lrc)
+}
+
+/// Triggers a browser download of `contents` as a file named `filename`, via a Blob object URL
+/// and a hidden, programmatically-clicked `<a download>` element.
+fn download_text_file(filename: &str, contents: &str) {
+    let Some(window) = web_sys::window() else { return };
+    let Some(document) = window.document() else { return };
+
+    let parts = js_sys::Array::new();
+    parts.push(&JsValue::from_str(contents));
+
+    let mut blob_options = web_sys::BlobPropertyBag::new();
+    blob_options.type_("text/plain");
+    let Ok(blob) = web_sys::Blob::new_with_str_sequence_and_options(&parts, &blob_options) else { return };
+    let Ok(url) = web_sys::Url::create_object_url_with_blob(&blob) else { return };
+
+    if let Ok(anchor) = document.create_element("a") {
+        let anchor: web_sys::HtmlAnchorElement = anchor.unchecked_into();
+        anchor.set_href(&url);
+        anchor.set_download(filename);
+        // some browsers (historically Firefox/Safari) only honour `.click()` on an anchor
+        // that's actually in the document, so attach it before clicking and clean up after
+        if let Some(body) = document.body() {
+            let _ = body.append_child(&anchor);
+            anchor.click();
+            let _ = body.remove_child(&anchor);
+        }
+    }
+
+    let _ = web_sys::Url::revoke_object_url(&url);
+}
+
+/// Speaks the verse at `model.playback.current`, if "read aloud" has anything to speak there.
+fn speak_current_verse(model: &Model) {
+    if let Some(verse) = sing(model.range.parsed.start, model.range.parsed.end).lines().nth(model.playback.current) {
+        speak(verse);
+    }
+}
+
+/// Subscribes to a `Tick` every `tempo_ms`, returning the handle that keeps it alive.
+fn start_ticker(orders: &mut impl Orders<Msg>, tempo_ms: u32) -> StreamHandle {
+    orders.stream_with_handle(streams::interval(tempo_ms, || Msg::Tick))
+}
+
+/// Speaks `text` aloud via the browser's speech synthesis API, if available.
+fn speak(text: &str) {
+    let Some(window) = web_sys::window() else { return };
+    if let Ok(synth) = window.speech_synthesis() {
+        if let Ok(utterance) = web_sys::SpeechSynthesisUtterance::new_with_text(text) {
+            synth.speak(&utterance);
+        }
     }
 }
 
@@ -160,18 +351,10 @@ fn view(model: &Model) -> Vec<Node<Msg>> {
         Header::new("Beer Song"),
         section![
             C!["content"],
+            C![model.theme.resolved_class(model.system_prefers_dark)],
             div![
                 C!["flexbox-row"],
-                input![
-                    C!["from"],
-                    attrs!{ At::Type => "number", At::Value => model.start.raw },
-                    input_ev(Ev::Input, Msg::UpdateStart)
-                ],
-                input![
-                    C!["to"],
-                    attrs!{ At::Type => "number", At::Value => model.end.raw },
-                    input_ev(Ev::Input, Msg::UpdateEnd)
-                ]
+                model.range.view("Range", Msg::UpdateRange),
             ],
             div![
                 C!["controls"],
@@ -184,14 +367,85 @@ fn view(model: &Model) -> Vec<Node<Msg>> {
                     C!["next-verse"],
                     "Next Verse",
                     ev(Ev::Click, |_| Msg::NextVerse)
+                ],
+                button![
+                    C!["play"],
+                    attrs!{ At::Disabled => model.playback.playing.as_at_value() },
+                    "Play",
+                    ev(Ev::Click, |_| Msg::Play)
+                ],
+                button![
+                    C!["pause"],
+                    attrs!{ At::Disabled => (!model.playback.playing).as_at_value() },
+                    "Pause",
+                    ev(Ev::Click, |_| Msg::Pause)
+                ],
+                button![
+                    C!["stop"],
+                    "Stop",
+                    ev(Ev::Click, |_| Msg::Stop)
+                ],
+                label![
+                    C!["tempo"],
+                    "Tempo (ms)",
+                    input![
+                        attrs!{
+                            At::Type => "range",
+                            At::Min => 200,
+                            At::Max => 5000,
+                            At::Step => 100,
+                            At::Value => model.playback.tempo_ms,
+                        },
+                        input_ev(Ev::Input, Msg::SetTempo)
+                    ]
+                ],
+                label![
+                    C!["read-aloud"],
+                    input![
+                        attrs!{ At::Type => "checkbox" },
+                        attrs!{ At::Checked => model.playback.read_aloud.as_at_value() },
+                        ev(Ev::Click, |_| Msg::ToggleReadAloud)
+                    ],
+                    "Read aloud"
+                ],
+                button![
+                    C!["export-lrc"],
+                    "Export .lrc",
+                    ev(Ev::Click, |_| Msg::ExportLrc)
                 ]
             ],
             ul![
                 C!["song"],
-                sing(model.start.parsed, model.end.parsed).lines().map(|verse| li![verse]).collect::<Vec<Node<Msg>>>()
+                sing(model.range.parsed.start, model.range.parsed.end).lines().enumerate().map(|(i, verse)| {
+                    li![
+                        IF!(i == model.playback.current => C!["current-verse"]),
+                        verse
+                    ]
+                }).collect::<Vec<Node<Msg>>>()
             ],
         ]
         Footer::new("Beer Song", "Choose a range of verses of \"the beer song\" to 'sing'"),
+        div![
+            C!["theme-toggle"],
+            button![
+                C!["theme-light"],
+                IF!(model.theme == Theme::Light => C!["active"]),
+                "Light",
+                ev(Ev::Click, |_| Msg::SetTheme(Theme::Light))
+            ],
+            button![
+                C!["theme-dark"],
+                IF!(model.theme == Theme::Dark => C!["active"]),
+                "Dark",
+                ev(Ev::Click, |_| Msg::SetTheme(Theme::Dark))
+            ],
+            button![
+                C!["theme-auto"],
+                IF!(model.theme == Theme::Auto => C!["active"]),
+                "Auto",
+                ev(Ev::Click, |_| Msg::SetTheme(Theme::Auto))
+            ],
+        ],
     ]
 }
 
@@ -0,0 +1,143 @@
+//! Parses free-form range strings like `"99-0"`, `"99..0"` or `"99 to 0"` into a validated
+//! `Range`, so the UI can offer a single text box instead of two inter-dependent number inputs.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// A validated, descending verse range: `start >= end`, both clamped into `0..=99`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Range {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Why a range string failed to parse, with enough detail for a helpful error message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseRangeError {
+    ExpectedNumber,
+    ExpectedSeparator,
+    TrailingInput,
+    StartBeforeEnd { start: u32, end: u32 },
+}
+
+impl fmt::Display for ParseRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseRangeError::ExpectedNumber => write!(f, "expected a number"),
+            ParseRangeError::ExpectedSeparator => {
+                write!(f, "expected a separator ('-', '..' or 'to') between the two numbers")
+            },
+            ParseRangeError::TrailingInput => write!(f, "unexpected text after the range"),
+            ParseRangeError::StartBeforeEnd { start, end } => {
+                write!(f, "start ({start}) must not come before end ({end})")
+            },
+        }
+    }
+}
+
+impl std::error::Error for ParseRangeError {}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+impl FromStr for Range {
+    type Err = ParseRangeError;
+
+    /// skip whitespace, a leading unsigned integer, whitespace, a separator (`-`, `..` or `to`),
+    /// whitespace, a trailing unsigned integer, then require end-of-input
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let rest = input.trim_start();
+        let (start, rest) = parse_u32(rest)?;
+        let rest = parse_separator(rest.trim_start())?;
+        let (end, rest) = parse_u32(rest.trim_start())?;
+
+        if !rest.trim_start().is_empty() {
+            return Err(ParseRangeError::TrailingInput);
+        }
+
+        let start = start.min(99);
+        let end = end.min(99);
+        if start < end {
+            return Err(ParseRangeError::StartBeforeEnd { start, end });
+        }
+
+        Ok(Range { start, end })
+    }
+}
+
+/// consumes a leading run of ASCII digits, returning the parsed number and the remaining input
+fn parse_u32(input: &str) -> Result<(u32, &str), ParseRangeError> {
+    let digits_len = input.chars().take_while(char::is_ascii_digit).count();
+    if digits_len == 0 {
+        return Err(ParseRangeError::ExpectedNumber);
+    }
+    let (digits, rest) = input.split_at(digits_len);
+    let number = digits.parse().map_err(|_| ParseRangeError::ExpectedNumber)?;
+    Ok((number, rest))
+}
+
+/// consumes one of the supported separators (`-`, `..`, or the word `to`)
+fn parse_separator(input: &str) -> Result<&str, ParseRangeError> {
+    [".." , "-", "to"]
+        .into_iter()
+        .find_map(|separator| input.strip_prefix(separator))
+        .ok_or(ParseRangeError::ExpectedSeparator)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_dash_separator() {
+        assert_eq!("99-0".parse(), Ok(Range { start: 99, end: 0 }));
+    }
+
+    #[test]
+    fn parses_dots_separator() {
+        assert_eq!("12..3".parse(), Ok(Range { start: 12, end: 3 }));
+    }
+
+    #[test]
+    fn parses_to_separator() {
+        assert_eq!("5 to 2".parse(), Ok(Range { start: 5, end: 2 }));
+    }
+
+    #[test]
+    fn allows_whitespace_around_numbers_and_separator() {
+        assert_eq!("  99  ..  0  ".parse(), Ok(Range { start: 99, end: 0 }));
+    }
+
+    #[test]
+    fn clamps_numbers_above_99() {
+        assert_eq!("150-0".parse(), Ok(Range { start: 99, end: 0 }));
+    }
+
+    #[test]
+    fn rejects_start_before_end() {
+        assert_eq!("0-5".parse::<Range>(), Err(ParseRangeError::StartBeforeEnd { start: 0, end: 5 }));
+    }
+
+    #[test]
+    fn rejects_missing_trailing_number() {
+        assert_eq!("99-".parse::<Range>(), Err(ParseRangeError::ExpectedNumber));
+    }
+
+    #[test]
+    fn rejects_missing_separator() {
+        assert_eq!("99 0".parse::<Range>(), Err(ParseRangeError::ExpectedSeparator));
+    }
+
+    #[test]
+    fn rejects_trailing_garbage() {
+        assert_eq!("5-2 please".parse::<Range>(), Err(ParseRangeError::TrailingInput));
+    }
+
+    #[test]
+    fn rejects_non_numeric_input() {
+        assert_eq!("abc".parse::<Range>(), Err(ParseRangeError::ExpectedNumber));
+    }
+}